@@ -10,14 +10,27 @@ use core::ptr::NonNull;
 /// 对齐大小（最小块大小）
 const ALIGNMENT: usize = 3; // 对齐单位是 2^3 = 8 字节
 /// 一级索引数量
-const FL_INDEX_COUNT: usize = 29; // 最大支持块大小 2GB
+const FL_INDEX_COUNT: usize = 29; // 最大支持块大小约 64GB（小于 2^36 字节）
+/// 二级索引的粒度（log2）：每个一级索引被细分为 2^SL_INDEX_COUNT_LOG2 个子类
+const SL_INDEX_COUNT_LOG2: usize = 5;
 /// 二级索引数量
-const SL_INDEX_COUNT: usize = 32; // 每一级索引分为 32 个子类
+const SL_INDEX_COUNT: usize = 1 << SL_INDEX_COUNT_LOG2; // 每一级索引分为 32 个子类
+/// 小于该阈值的块大小使用专门的「小块」一级索引（fl = 0），sl 按 [`ALIGNMENT`] 粒度线性排布，
+/// 避免对数分桶在 `fl < SL_INDEX_COUNT_LOG2` 时移位下溢
+const SMALL_BLOCK_SIZE: usize = 1 << (SL_INDEX_COUNT_LOG2 + ALIGNMENT);
+/// 空闲标志位，借用 `size` 的最低位。由于所有块都按 8 字节对齐，
+/// 块大小的低 3 位恒为 0，可以安全地复用最低位来记录该块是否空闲。
+const FREE_BIT: usize = 1;
+/// 可同时管理的内存区域（由 `init`/`add_memory` 注册）数量上限
+const MAX_REGIONS: usize = 8;
 
 pub struct LabByteAllocator {
     fl_bitmap: u32,
     sl_bitmap: [u32; FL_INDEX_COUNT],
     free_blocks: [[Option<NonNull<FreeBlockHeader>>; SL_INDEX_COUNT]; FL_INDEX_COUNT],
+    /// 每个区域的 `[start, end)`，用于阻止物理合并跨越 `add_memory` 注册的区域边界
+    regions: [(usize, usize); MAX_REGIONS],
+    region_count: usize,
     total_memory: usize,
     used_memory: usize,
 }
@@ -31,33 +44,88 @@ impl LabByteAllocator {
             fl_bitmap: 0,
             sl_bitmap: [0; FL_INDEX_COUNT],
             free_blocks: [[None; SL_INDEX_COUNT]; FL_INDEX_COUNT],
+            regions: [(0, 0); MAX_REGIONS],
+            region_count: 0,
             total_memory: 0,
             used_memory: 0,
         }
     }
 
-    /// 映射大小到索引
-    fn mapping(size: usize) -> (usize, usize) {
-        // 计算高位索引 fl
-        let mut fl = 0;
-        let mut temp_size = size;
-        while temp_size > 1 {
-            fl += 1;
-            temp_size >>= 1;
+    /// 注册一个新的内存区域，供合并时做边界检查
+    fn add_region(&mut self, start: usize, end: usize) {
+        if self.region_count < MAX_REGIONS {
+            self.regions[self.region_count] = (start, end);
+            self.region_count += 1;
         }
+    }
+
+    /// 返回包含给定地址的区域的 `[start, end)`
+    fn region_containing(&self, addr: usize) -> Option<(usize, usize)> {
+        self.regions[..self.region_count]
+            .iter()
+            .copied()
+            .find(|&(start, end)| addr >= start && addr < end)
+    }
 
-        // 计算二级索引 sl
-        let sl = (size >> (fl - SL_INDEX_COUNT)) & (SL_INDEX_COUNT - 1);
+    /// 初始化一块新内存区域为一个完整的空闲块
+    fn init_region(&mut self, start: usize, size: usize) {
+        let aligned_start = (start + (1 << ALIGNMENT) - 1) & !((1 << ALIGNMENT) - 1);
+        let aligned_size = size & !((1 << ALIGNMENT) - 1);
 
-        (fl - 1, sl) // fl - 1 是因为 fl 从 1 开始计数
+        let block = aligned_start as *mut FreeBlockHeader;
+        unsafe {
+            (*block).common.set_size_free(aligned_size, true);
+            (*block).common.prev_phys_blk = None;
+            (*block).next_free = None;
+            (*block).prev_free = None;
+        }
+        self.add_region(aligned_start, aligned_start + aligned_size);
+        self.insert_free_block(unsafe { NonNull::new_unchecked(block) });
     }
 
-    /// 插入空闲块
-    fn insert_free_block(&mut self, block: NonNull<FreeBlockHeader>) {
+    /// `floor(log2(size))`，要求 `size >= 1`
+    fn floor_log2(size: usize) -> usize {
+        usize::BITS as usize - 1 - size.leading_zeros() as usize
+    }
+
+    /// 把大小映射到 `(fl, sl)` 索引，向下取整（用于插入：块的真实大小落在所选子类范围内）
+    fn mapping_insert(size: usize) -> (usize, usize) {
+        if size < SMALL_BLOCK_SIZE {
+            // 小块：fl 固定为 0，sl 直接按 ALIGNMENT 粒度线性排布，不做对数分桶
+            (0, size >> ALIGNMENT)
+        } else {
+            let fl_raw = Self::floor_log2(size);
+            let sl = (size >> (fl_raw - SL_INDEX_COUNT_LOG2)) & (SL_INDEX_COUNT - 1);
+            // 归一化，使得大块的最小一级索引紧接在小块的 fl = 0 之后
+            let fl = fl_raw - Self::floor_log2(SMALL_BLOCK_SIZE) + 1;
+            (fl, sl)
+        }
+    }
+
+    /// 把大小映射到 `(fl, sl)` 索引，向上取整到下一个子类，
+    /// 保证该子类里的块一定不小于请求的 `size`（用于查找）
+    fn mapping_search(size: usize) -> (usize, usize) {
+        if size < SMALL_BLOCK_SIZE {
+            Self::mapping_insert(size)
+        } else {
+            let fl_raw = Self::floor_log2(size);
+            let round = (1usize << (fl_raw - SL_INDEX_COUNT_LOG2)) - 1;
+            Self::mapping_insert(size + round)
+        }
+    }
+
+    /// 插入空闲块：把它挂到 `(fl, sl)` 链表的头部
+    fn insert_free_block(&mut self, mut block: NonNull<FreeBlockHeader>) {
         unsafe {
-            let block_ref = block.as_ref();
-            let size = block_ref.common.size;
-            let (fl, sl) = Self::mapping(size);
+            let size = block.as_ref().common.size();
+            let (fl, sl) = Self::mapping_insert(size);
+
+            let old_head = self.free_blocks[fl][sl];
+            block.as_mut().prev_free = None;
+            block.as_mut().next_free = old_head;
+            if let Some(mut head) = old_head {
+                head.as_mut().prev_free = Some(block);
+            }
 
             self.free_blocks[fl][sl] = Some(block);
             self.fl_bitmap |= 1 << fl;
@@ -65,24 +133,49 @@ impl LabByteAllocator {
         }
     }
 
-    /// 从空闲链表中移除块
+    /// 从 `(fl, sl)` 链表中摘下头部空闲块
     fn remove_free_block(&mut self, fl: usize, sl: usize) -> Option<NonNull<FreeBlockHeader>> {
-        let block = self.free_blocks[fl][sl];
-        self.free_blocks[fl][sl] = None;
+        let block = self.free_blocks[fl][sl]?;
+        unsafe { self.unlink(fl, sl, block) };
+        Some(block)
+    }
 
-        if self.free_blocks[fl].iter().all(Option::is_none) {
-            self.fl_bitmap &= !(1 << fl);
+    /// 从所在链表中移除任意一个空闲块（不要求它是链表头）
+    fn unlink_block(&mut self, block: NonNull<FreeBlockHeader>) {
+        unsafe {
+            let size = block.as_ref().common.size();
+            let (fl, sl) = Self::mapping_insert(size);
+            self.unlink(fl, sl, block);
         }
-        if block.is_some() {
-            self.sl_bitmap[fl] &= !(1 << sl);
+    }
+
+    /// 把 `block` 从 `(fl, sl)` 链表中摘除，拼接其前驱/后继，
+    /// 仅当链表变空时才清除对应的 `sl_bitmap`/`fl_bitmap` 位
+    unsafe fn unlink(&mut self, fl: usize, sl: usize, mut block: NonNull<FreeBlockHeader>) {
+        let prev = block.as_ref().prev_free;
+        let next = block.as_ref().next_free;
+
+        match prev {
+            Some(mut prev) => prev.as_mut().next_free = next,
+            None => self.free_blocks[fl][sl] = next,
+        }
+        if let Some(mut next) = next {
+            next.as_mut().prev_free = prev;
         }
+        block.as_mut().next_free = None;
+        block.as_mut().prev_free = None;
 
-        block
+        if self.free_blocks[fl][sl].is_none() {
+            self.sl_bitmap[fl] &= !(1 << sl);
+            if self.sl_bitmap[fl] == 0 {
+                self.fl_bitmap &= !(1 << fl);
+            }
+        }
     }
 
-    /// 查找合适的块
+    /// 查找合适的块（使用向上取整的 [`Self::mapping_search`]，保证找到的子类里的块一定够大）
     fn find_suitable_block(&self, size: usize) -> Option<(usize, usize)> {
-        let (fl, sl) = Self::mapping(size);
+        let (fl, sl) = Self::mapping_search(size);
 
         for i in fl..FL_INDEX_COUNT {
             let sl_mask = if i == fl { self.sl_bitmap[i] & !((1 << sl) - 1) } else { self.sl_bitmap[i] };
@@ -94,36 +187,29 @@ impl LabByteAllocator {
 
         None
     }
+
+    /// 把物理上紧跟在 `[addr, addr + size)` 之后的块（如果存在且落在同一区域内）的
+    /// `prev_phys_blk` 指向 `addr`。用于在分裂/合并后保持边界标记链条的正确性。
+    unsafe fn relink_next_phys(&self, addr: usize, size: usize) {
+        if let Some((_, region_end)) = self.region_containing(addr) {
+            let next_addr = addr + size;
+            if next_addr < region_end {
+                let next = next_addr as *mut BlockHeader;
+                (*next).prev_phys_blk = Some(NonNull::new_unchecked(addr as *mut BlockHeader));
+            }
+        }
+    }
 }
 
 impl BaseAllocator for LabByteAllocator {
     fn init(&mut self, start: usize, size: usize) {
-        let aligned_start = (start + (1 << ALIGNMENT) - 1) & !((1 << ALIGNMENT) - 1);
-        let aligned_size = size & !((1 << ALIGNMENT) - 1);
-
-        let block = aligned_start as *mut FreeBlockHeader;
-        unsafe {
-            (*block).common.size = aligned_size;
-            (*block).common.prev_phys_blk = None;
-            (*block).next_free = None;
-            (*block).prev_free = None;
-        }
-        self.insert_free_block(unsafe { NonNull::new_unchecked(block) });
-        self.total_memory = aligned_size;
+        self.init_region(start, size);
+        self.total_memory = size;
     }
 
     fn add_memory(&mut self, start: usize, size: usize) -> AllocResult {
-        let aligned_start = (start + (1 << ALIGNMENT) - 1) & !((1 << ALIGNMENT) - 1);
-        let aligned_size = size & !((1 << ALIGNMENT) - 1);
-
-        let block = aligned_start as *mut FreeBlockHeader;
-        unsafe {
-            (*block).common.size = aligned_size;
-            (*block).common.prev_phys_blk = None;
-            (*block).next_free = None;
-            (*block).prev_free = None;
-        }
-        self.insert_free_block(unsafe { NonNull::new_unchecked(block) });
+        self.init_region(start, size);
+        self.total_memory += size;
 
         Ok(())
     }
@@ -136,17 +222,33 @@ impl ByteAllocator for LabByteAllocator {
         if let Some((fl, sl)) = self.find_suitable_block(size) {
             if let Some(block) = self.remove_free_block(fl, sl) {
                 unsafe {
-                    let block_ref = block.as_ref();
-                    let block_size = block_ref.common.size;
+                    let block_addr = block.as_ptr() as usize;
+                    let block_size = block.as_ref().common.size();
 
                     if block_size > size + core::mem::size_of::<FreeBlockHeader>() {
-                        let new_block = (block.as_ptr() as usize + size) as *mut FreeBlockHeader;
-                        (*new_block).common.size = block_size - size;
-                        self.insert_free_block(NonNull::new_unchecked(new_block));
+                        let remainder_addr = block_addr + size;
+                        let remainder_size = block_size - size;
+                        let remainder = remainder_addr as *mut FreeBlockHeader;
+                        (*remainder).common.set_size_free(remainder_size, true);
+                        (*remainder).common.prev_phys_blk =
+                            Some(NonNull::new_unchecked(block_addr as *mut BlockHeader));
+                        (*remainder).next_free = None;
+                        (*remainder).prev_free = None;
+
+                        // 紧跟在原块之后的块（如果有）现在要改为指向新产生的余块
+                        self.relink_next_phys(remainder_addr, remainder_size);
+
+                        self.insert_free_block(NonNull::new_unchecked(remainder));
+
+                        (*block.as_ptr()).common.set_size_free(size, false);
+                    } else {
+                        // 没有拆分，保持块原本的物理大小不变，否则块尾会留下一段
+                        // 没有头部、不受管理的空隙，破坏物理边界标记
+                        (*block.as_ptr()).common.set_size_free(block_size, false);
                     }
 
                     self.used_memory += size;
-                    return Ok(NonNull::new_unchecked(block.as_ptr() as *mut u8));
+                    return Ok(NonNull::new_unchecked(block_addr as *mut u8));
                 }
             }
         }
@@ -156,13 +258,54 @@ impl ByteAllocator for LabByteAllocator {
 
     fn dealloc(&mut self, pos: NonNull<u8>, layout: Layout) {
         let size = (layout.size() + (1 << ALIGNMENT) - 1) & !((1 << ALIGNMENT) - 1);
-        let block = pos.as_ptr() as *mut FreeBlockHeader;
+        let mut addr = pos.as_ptr() as usize;
 
         unsafe {
-            (*block).common.size = size;
-            self.insert_free_block(NonNull::new_unchecked(block));
-            self.used_memory -= size;
+            // 块的真实物理大小记在它自己的头部里，可能因为 alloc 时没有拆分
+            // 而比请求的 `size` 更大，不能直接拿 `size` 当作物理范围来用
+            let mut merged_size = (*(addr as *const BlockHeader)).size();
+            let region = self.region_containing(addr);
+
+            // 与物理右邻居合并
+            if let Some((_, region_end)) = region {
+                let right_addr = addr + merged_size;
+                if right_addr < region_end {
+                    let right = right_addr as *mut BlockHeader;
+                    if (*right).is_free() {
+                        let right_size = (*right).size();
+                        let right_block = NonNull::new_unchecked(right_addr as *mut FreeBlockHeader);
+                        self.unlink_block(right_block);
+                        merged_size += right_size;
+                    }
+                }
+            }
+
+            // 与物理左邻居合并
+            if let Some((region_start, _)) = region {
+                let header = addr as *mut BlockHeader;
+                if let Some(prev) = (*header).prev_phys_blk {
+                    let prev_addr = prev.as_ptr() as usize;
+                    if prev_addr >= region_start && prev.as_ref().is_free() {
+                        let prev_size = prev.as_ref().size();
+                        let prev_block = NonNull::new_unchecked(prev_addr as *mut FreeBlockHeader);
+                        self.unlink_block(prev_block);
+                        addr = prev_addr;
+                        merged_size += prev_size;
+                    }
+                }
+            }
+
+            let merged = addr as *mut FreeBlockHeader;
+            (*merged).common.set_size_free(merged_size, true);
+            (*merged).next_free = None;
+            (*merged).prev_free = None;
+
+            self.relink_next_phys(addr, merged_size);
+
+            self.insert_free_block(NonNull::new_unchecked(merged));
         }
+
+        self.used_memory -= size;
     }
 
     fn total_bytes(&self) -> usize {
@@ -180,10 +323,28 @@ impl ByteAllocator for LabByteAllocator {
 
 #[repr(C)]
 struct BlockHeader {
+    /// 块大小，最低位复用为 FREE 标志（见 [`FREE_BIT`]），读取真实大小需通过 [`BlockHeader::size`]
     size: usize,
     prev_phys_blk: Option<NonNull<BlockHeader>>,
 }
 
+impl BlockHeader {
+    /// 真实块大小（已掩掉 FREE 标志位）
+    fn size(&self) -> usize {
+        self.size & !FREE_BIT
+    }
+
+    /// 该块当前是否空闲
+    fn is_free(&self) -> bool {
+        self.size & FREE_BIT != 0
+    }
+
+    /// 同时设置块大小与空闲标志
+    fn set_size_free(&mut self, size: usize, free: bool) {
+        self.size = size | if free { FREE_BIT } else { 0 };
+    }
+}
+
 #[repr(C)]
 struct FreeBlockHeader {
     common: BlockHeader,
@@ -0,0 +1,261 @@
+#![no_std]
+
+use allocator::{AllocResult, AllocError, BaseAllocator, PageAllocator};
+
+/// Number of `u32` words in the leaf bitmap, i.e. `LEAF_WORDS * 32` pages is
+/// the largest region a single [`HierarchicalBitmap`] can index (1M bits ==
+/// 4GB at 4KB pages).
+const LEAF_WORDS: usize = 1 << 15;
+const L1_WORDS: usize = LEAF_WORDS / 32;
+const L2_WORDS: usize = L1_WORDS / 32;
+/// The root summary always fits in a single `u32` (`L2_WORDS / 32 == 1`).
+const MAX_BITS: usize = LEAF_WORDS * 32;
+
+/// A three-level summary bitmap over a flat bitmap of up to [`MAX_BITS`]
+/// bits, giving O(1)-ish (bounded by a constant 4 levels) "find first set
+/// bit" instead of a linear scan.
+///
+/// A set bit at level `L` means "at least one set bit somewhere in the
+/// corresponding 32-entry subtree at level `L - 1`"; the leaf level holds the
+/// actual bits. Here a set bit means "free".
+struct HierarchicalBitmap {
+    leaf: [u32; LEAF_WORDS],
+    l1: [u32; L1_WORDS],
+    l2: [u32; L2_WORDS],
+    /// Root summary word; bit `i` summarizes `l2[i]`.
+    root: u32,
+    /// Number of leaf bits actually in use; bits beyond this are always 0.
+    capacity: usize,
+}
+
+impl HierarchicalBitmap {
+    /// Creates a bitmap with the first `capacity` bits marked free.
+    fn new(capacity: usize) -> Self {
+        let mut bm = Self {
+            leaf: [0; LEAF_WORDS],
+            l1: [0; L1_WORDS],
+            l2: [0; L2_WORDS],
+            root: 0,
+            capacity,
+        };
+        for i in 0..capacity {
+            bm.mark_free(i);
+        }
+        bm
+    }
+
+    fn is_free(&self, idx: usize) -> bool {
+        self.leaf[idx / 32] & (1 << (idx % 32)) != 0
+    }
+
+    /// Marks `idx` free and re-sets every ancestor summary bit on the path
+    /// up to the root.
+    fn mark_free(&mut self, idx: usize) {
+        let (l1_idx, i1, l2_idx, i2, i3) = Self::path(idx);
+        self.leaf[l1_idx] |= 1 << i1;
+        self.l1[l2_idx] |= 1 << i2;
+        self.l2[i3] |= 1 << (l2_idx % 32);
+        self.root |= 1 << i3;
+    }
+
+    /// Marks `idx` used, clearing ancestor summary bits whose subtree became
+    /// entirely empty as a result.
+    fn mark_used(&mut self, idx: usize) {
+        let (l1_idx, i1, l2_idx, i2, i3) = Self::path(idx);
+        self.leaf[l1_idx] &= !(1 << i1);
+        if self.leaf[l1_idx] != 0 {
+            return;
+        }
+        self.l1[l2_idx] &= !(1 << i2);
+        if self.l1[l2_idx] != 0 {
+            return;
+        }
+        self.l2[i3] &= !(1 << (l2_idx % 32));
+        if self.l2[i3] != 0 {
+            return;
+        }
+        self.root &= !(1 << i3);
+    }
+
+    /// Splits a leaf index into `(leaf_word, bit_in_leaf_word, l1_word, bit_in_l1_word, l2_word)`.
+    /// The bit of `l1_word` within its `l2` word is `l1_word % 32`, and
+    /// `l2_word` is also its bit index in `root`.
+    fn path(idx: usize) -> (usize, usize, usize, usize, usize) {
+        let l1_idx = idx / 32;
+        let i1 = idx % 32;
+        let l2_idx = l1_idx / 32;
+        let i2 = l1_idx % 32;
+        let i3 = l2_idx / 32;
+        (l1_idx, i1, l2_idx, i2, i3)
+    }
+
+    /// Finds and claims the first free bit, descending from the root using
+    /// `trailing_zeros` at each of the four levels.
+    fn alloc_first(&mut self) -> Option<usize> {
+        if self.root == 0 {
+            return None;
+        }
+        let i3 = self.root.trailing_zeros() as usize;
+        let i2 = self.l2[i3].trailing_zeros() as usize;
+        let l2_idx = i3 * 32 + i2;
+        let i1 = self.l1[l2_idx].trailing_zeros() as usize;
+        let l1_idx = l2_idx * 32 + i1;
+        let bit = self.leaf[l1_idx].trailing_zeros() as usize;
+        let idx = l1_idx * 32 + bit;
+
+        self.mark_used(idx);
+        Some(idx)
+    }
+}
+
+/// Maximum number of disjoint memory regions that can be registered via
+/// `add_memory`.
+const MAX_REGIONS: usize = 4;
+
+struct Region {
+    base: usize,
+    num_pages: usize,
+    used_pages: usize,
+    bitmap: HierarchicalBitmap,
+}
+
+/// A page allocator whose free/used state is tracked by a
+/// [`HierarchicalBitmap`] per region, so finding a free page is O(1) in the
+/// number of pages rather than a linear scan.
+///
+/// Contiguous multi-page requests still need to find a run of `n` free bits,
+/// which falls back to scanning the leaf bitmap directly; the hierarchical
+/// index's win is for the common single-page case.
+pub struct BitmapPageAllocator<const PAGE_SIZE: usize> {
+    regions: [Option<Region>; MAX_REGIONS],
+    region_count: usize,
+}
+
+unsafe impl<const PAGE_SIZE: usize> Send for BitmapPageAllocator<PAGE_SIZE> {}
+unsafe impl<const PAGE_SIZE: usize> Sync for BitmapPageAllocator<PAGE_SIZE> {}
+
+impl<const PAGE_SIZE: usize> BitmapPageAllocator<PAGE_SIZE> {
+    pub const fn new() -> Self {
+        const NONE_REGION: Option<Region> = None;
+        Self {
+            regions: [NONE_REGION; MAX_REGIONS],
+            region_count: 0,
+        }
+    }
+
+    fn add_region(&mut self, start: usize, size: usize) -> AllocResult {
+        if self.region_count >= MAX_REGIONS {
+            return Err(AllocError::NoMemory);
+        }
+
+        let aligned_start = (start + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+        let aligned_end = (start + size) & !(PAGE_SIZE - 1);
+        if aligned_end <= aligned_start {
+            return Err(AllocError::InvalidParam);
+        }
+        let num_pages = ((aligned_end - aligned_start) / PAGE_SIZE).min(MAX_BITS);
+
+        self.regions[self.region_count] = Some(Region {
+            base: aligned_start,
+            num_pages,
+            used_pages: 0,
+            bitmap: HierarchicalBitmap::new(num_pages),
+        });
+        self.region_count += 1;
+        Ok(())
+    }
+
+    fn region_for_mut(&mut self, addr: usize) -> Option<&mut Region> {
+        self.regions[..self.region_count]
+            .iter_mut()
+            .flatten()
+            .find(|r| addr >= r.base && addr < r.base + r.num_pages * PAGE_SIZE)
+    }
+
+    /// Scans `region`'s leaf bitmap for `n` contiguous free pages whose start
+    /// satisfies `align_pages`, used for multi-page requests.
+    fn find_contiguous(region: &Region, n: usize, align_pages: usize) -> Option<usize> {
+        let mut start = 0;
+        'outer: while start + n <= region.num_pages {
+            if start % align_pages != 0 {
+                start += 1;
+                continue;
+            }
+            for i in 0..n {
+                if !region.bitmap.is_free(start + i) {
+                    start += i + 1;
+                    continue 'outer;
+                }
+            }
+            return Some(start);
+        }
+        None
+    }
+}
+
+impl<const PAGE_SIZE: usize> BaseAllocator for BitmapPageAllocator<PAGE_SIZE> {
+    fn init(&mut self, start: usize, size: usize) {
+        let _ = self.add_region(start, size);
+    }
+
+    fn add_memory(&mut self, start: usize, size: usize) -> AllocResult {
+        self.add_region(start, size)
+    }
+}
+
+impl<const PAGE_SIZE: usize> PageAllocator for BitmapPageAllocator<PAGE_SIZE> {
+    const PAGE_SIZE: usize = PAGE_SIZE;
+
+    fn alloc_pages(&mut self, num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
+        let align_pages = ((1usize << align_pow2) / PAGE_SIZE).max(1);
+
+        for region in self.regions[..self.region_count].iter_mut().flatten() {
+            let start = if num_pages == 1 && align_pages == 1 {
+                region.bitmap.alloc_first()
+            } else if let Some(start) = Self::find_contiguous(region, num_pages, align_pages) {
+                for i in start..start + num_pages {
+                    region.bitmap.mark_used(i);
+                }
+                Some(start)
+            } else {
+                None
+            };
+
+            if let Some(start) = start {
+                region.used_pages += num_pages;
+                return Ok(region.base + start * PAGE_SIZE);
+            }
+        }
+        Err(AllocError::NoMemory)
+    }
+
+    fn dealloc_pages(&mut self, pos: usize, num_pages: usize) {
+        if let Some(region) = self.region_for_mut(pos) {
+            let start = (pos - region.base) / PAGE_SIZE;
+            for i in start..start + num_pages {
+                region.bitmap.mark_free(i);
+            }
+            region.used_pages -= num_pages;
+        }
+    }
+
+    fn total_pages(&self) -> usize {
+        self.regions[..self.region_count]
+            .iter()
+            .flatten()
+            .map(|r| r.num_pages)
+            .sum()
+    }
+
+    fn used_pages(&self) -> usize {
+        self.regions[..self.region_count]
+            .iter()
+            .flatten()
+            .map(|r| r.used_pages)
+            .sum()
+    }
+
+    fn available_pages(&self) -> usize {
+        self.total_pages() - self.used_pages()
+    }
+}
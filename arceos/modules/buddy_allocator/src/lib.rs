@@ -0,0 +1,359 @@
+#![cfg_attr(not(test), no_std)]
+
+use allocator::{AllocResult, AllocError, BaseAllocator, PageAllocator};
+use core::ptr::NonNull;
+
+/// Number of distinct buddy orders supported, i.e. the largest allocatable
+/// block is `2^(FREELIST_SIZE - 1)` pages.
+const FREELIST_SIZE: usize = 32;
+/// Maximum number of disjoint memory regions that can be registered via
+/// `add_memory`.
+const MAX_REGIONS: usize = 8;
+
+/// An intrusive free-list node, stored in the first bytes of a free page.
+#[repr(C)]
+struct FreeNode {
+    next: Option<NonNull<FreeNode>>,
+}
+
+/// One contiguous memory region managed by the allocator.
+///
+/// A few pages at the front of the region are reserved to hold the per-page
+/// allocation bitmap and order table, so `base`/`num_pages` describe only the
+/// part of the region that is actually handed out.
+#[derive(Clone, Copy)]
+struct Region<const PAGE_SIZE: usize> {
+    base: usize,
+    num_pages: usize,
+    /// One bit per page: `1` means allocated, `0` means free.
+    bitmap: *mut u8,
+    /// One byte per page, valid only at the first page of an allocated
+    /// block: the order that block was allocated at. `alloc_pages` can bump
+    /// a request's order above `order_for(num_pages)` to satisfy a stricter
+    /// alignment, so `dealloc_pages` cannot recompute the order from
+    /// `num_pages` alone and needs this recorded instead.
+    order_of: *mut u8,
+    /// `free_head[k]` is the head of the free list of order-`k` blocks
+    /// (`2^k` pages), threaded through [`FreeNode`].
+    free_head: [Option<NonNull<FreeNode>>; FREELIST_SIZE],
+    used_pages: usize,
+}
+
+impl<const PAGE_SIZE: usize> Region<PAGE_SIZE> {
+    unsafe fn push_free(&mut self, addr: usize, order: usize) {
+        let node = addr as *mut FreeNode;
+        (*node).next = self.free_head[order];
+        self.free_head[order] = Some(NonNull::new_unchecked(node));
+    }
+
+    unsafe fn pop_free(&mut self, order: usize) -> Option<usize> {
+        let head = self.free_head[order]?;
+        self.free_head[order] = head.as_ref().next;
+        Some(head.as_ptr() as usize)
+    }
+
+    /// Removes a specific address from the order-`order` free list, if it is
+    /// currently on it.
+    unsafe fn unlink_free(&mut self, addr: usize, order: usize) -> bool {
+        let mut cur = self.free_head[order];
+        let mut prev: Option<NonNull<FreeNode>> = None;
+        while let Some(node) = cur {
+            if node.as_ptr() as usize == addr {
+                match prev {
+                    Some(mut p) => p.as_mut().next = node.as_ref().next,
+                    None => self.free_head[order] = node.as_ref().next,
+                }
+                return true;
+            }
+            prev = cur;
+            cur = node.as_ref().next;
+        }
+        false
+    }
+
+    fn page_index(&self, addr: usize) -> usize {
+        (addr - self.base) / PAGE_SIZE
+    }
+
+    fn buddy_of(&self, addr: usize, order: usize) -> usize {
+        let offset = addr - self.base;
+        self.base + (offset ^ (PAGE_SIZE << order))
+    }
+
+    fn mark(&mut self, page: usize, num_pages: usize, allocated: bool) {
+        for i in page..page + num_pages {
+            let byte = unsafe { &mut *self.bitmap.add(i / 8) };
+            if allocated {
+                *byte |= 1 << (i % 8);
+            } else {
+                *byte &= !(1 << (i % 8));
+            }
+        }
+    }
+
+    fn is_free_page(&self, page: usize) -> bool {
+        let byte = unsafe { *self.bitmap.add(page / 8) };
+        byte & (1 << (page % 8)) == 0
+    }
+
+    fn set_order(&mut self, page: usize, order: usize) {
+        unsafe { *self.order_of.add(page) = order as u8 };
+    }
+
+    fn order_at(&self, page: usize) -> usize {
+        unsafe { *self.order_of.add(page) as usize }
+    }
+
+    /// Finds the smallest non-empty free list with order `>= order`, splits
+    /// it down to exactly `order`, and returns the address of the resulting
+    /// block, pushing unused buddy halves back onto their own free lists.
+    unsafe fn alloc_order(&mut self, order: usize) -> Option<usize> {
+        let mut cur_order = (order..FREELIST_SIZE).find(|&o| self.free_head[o].is_some())?;
+        let addr = self.pop_free(cur_order).unwrap();
+
+        while cur_order > order {
+            cur_order -= 1;
+            let buddy = addr + (PAGE_SIZE << cur_order);
+            self.push_free(buddy, cur_order);
+        }
+        Some(addr)
+    }
+
+    /// Returns `[addr, addr + 2^order pages)` to the free lists, repeatedly
+    /// merging with the buddy block as long as it is free and of the same
+    /// order. The allocation bitmap is not touched here; the caller clears it.
+    unsafe fn dealloc_order(&mut self, mut addr: usize, mut order: usize) {
+        while order + 1 < FREELIST_SIZE {
+            let buddy = self.buddy_of(addr, order);
+            if buddy < self.base || buddy >= self.base + self.num_pages * PAGE_SIZE {
+                break;
+            }
+            let buddy_page = self.page_index(buddy);
+            if !self.is_free_page(buddy_page) || !self.unlink_free(buddy, order) {
+                break;
+            }
+            addr = addr.min(buddy);
+            order += 1;
+        }
+        self.push_free(addr, order);
+    }
+}
+
+/// A buddy-system page allocator.
+///
+/// Pages are handed out in power-of-two counts ("orders"). Freeing a block
+/// cheaply coalesces it with its buddy (computed via `addr XOR (2^order *
+/// PAGE_SIZE)`, relative to the region base) as long as the buddy is free and
+/// of the same order, repeating until no further merge is possible.
+pub struct BuddyPageAllocator<const PAGE_SIZE: usize> {
+    regions: [Option<Region<PAGE_SIZE>>; MAX_REGIONS],
+    region_count: usize,
+}
+
+unsafe impl<const PAGE_SIZE: usize> Send for BuddyPageAllocator<PAGE_SIZE> {}
+unsafe impl<const PAGE_SIZE: usize> Sync for BuddyPageAllocator<PAGE_SIZE> {}
+
+impl<const PAGE_SIZE: usize> BuddyPageAllocator<PAGE_SIZE> {
+    pub const fn new() -> Self {
+        Self {
+            regions: [None; MAX_REGIONS],
+            region_count: 0,
+        }
+    }
+
+    /// Smallest order `k` such that `2^k >= num_pages`.
+    fn order_for(num_pages: usize) -> usize {
+        let mut order = 0;
+        while (1usize << order) < num_pages {
+            order += 1;
+        }
+        order
+    }
+
+    /// Registers `[start, start + size)` as a new region, reserving enough
+    /// whole pages at the front to hold its allocation bitmap and order
+    /// table, then carves the rest into the largest power-of-two blocks that
+    /// fit and seeds the free lists with them.
+    fn add_region(&mut self, start: usize, size: usize) -> AllocResult {
+        if self.region_count >= MAX_REGIONS {
+            return Err(AllocError::NoMemory);
+        }
+
+        let aligned_start = (start + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+        let aligned_end = (start + size) & !(PAGE_SIZE - 1);
+        if aligned_end <= aligned_start {
+            return Err(AllocError::InvalidParam);
+        }
+        let total_pages = (aligned_end - aligned_start) / PAGE_SIZE;
+
+        let bitmap_bytes = total_pages.div_ceil(8);
+        // One order byte per page, right after the bitmap.
+        let meta_bytes = bitmap_bytes + total_pages;
+        let meta_pages = meta_bytes.div_ceil(PAGE_SIZE).max(1);
+        if meta_pages >= total_pages {
+            return Err(AllocError::NoMemory);
+        }
+
+        let bitmap = aligned_start as *mut u8;
+        unsafe { core::ptr::write_bytes(bitmap, 0, bitmap_bytes) };
+
+        let order_of = unsafe { bitmap.add(bitmap_bytes) };
+        unsafe { core::ptr::write_bytes(order_of, 0, total_pages) };
+
+        let base = aligned_start + meta_pages * PAGE_SIZE;
+        let num_pages = total_pages - meta_pages;
+
+        let mut region = Region::<PAGE_SIZE> {
+            base,
+            num_pages,
+            bitmap,
+            order_of,
+            free_head: [None; FREELIST_SIZE],
+            used_pages: 0,
+        };
+
+        // Carve the region into maximal, naturally-aligned power-of-two
+        // blocks and push each onto its order's free list.
+        let mut offset = 0;
+        while offset < num_pages {
+            let remaining = num_pages - offset;
+            let mut order = Self::order_for(remaining + 1).saturating_sub(1);
+            while order > 0 && (offset % (1 << order) != 0 || (1usize << order) > remaining) {
+                order -= 1;
+            }
+            order = order.min(FREELIST_SIZE - 1);
+            unsafe { region.push_free(base + offset * PAGE_SIZE, order) };
+            offset += 1 << order;
+        }
+
+        self.regions[self.region_count] = Some(region);
+        self.region_count += 1;
+        Ok(())
+    }
+
+    fn region_for_mut(&mut self, addr: usize) -> Option<&mut Region<PAGE_SIZE>> {
+        self.regions[..self.region_count]
+            .iter_mut()
+            .flatten()
+            .find(|r| addr >= r.base && addr < r.base + r.num_pages * PAGE_SIZE)
+    }
+}
+
+impl<const PAGE_SIZE: usize> BaseAllocator for BuddyPageAllocator<PAGE_SIZE> {
+    fn init(&mut self, start: usize, size: usize) {
+        let _ = self.add_region(start, size);
+    }
+
+    fn add_memory(&mut self, start: usize, size: usize) -> AllocResult {
+        self.add_region(start, size)
+    }
+}
+
+impl<const PAGE_SIZE: usize> PageAllocator for BuddyPageAllocator<PAGE_SIZE> {
+    const PAGE_SIZE: usize = PAGE_SIZE;
+
+    fn alloc_pages(&mut self, num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
+        let mut order = Self::order_for(num_pages.max(1));
+        // A block of order `k` is naturally aligned to `2^k * PAGE_SIZE`
+        // bytes, so bump the order up if a stricter alignment was requested.
+        let page_align_pow2 = PAGE_SIZE.trailing_zeros() as usize;
+        if align_pow2 > page_align_pow2 {
+            order = order.max(align_pow2 - page_align_pow2);
+        }
+        if order >= FREELIST_SIZE {
+            return Err(AllocError::InvalidParam);
+        }
+
+        for region in self.regions[..self.region_count].iter_mut().flatten() {
+            if let Some(addr) = unsafe { region.alloc_order(order) } {
+                let page = region.page_index(addr);
+                region.mark(page, 1 << order, true);
+                region.set_order(page, order);
+                region.used_pages += 1 << order;
+                return Ok(addr);
+            }
+        }
+        Err(AllocError::NoMemory)
+    }
+
+    fn dealloc_pages(&mut self, pos: usize, _num_pages: usize) {
+        if let Some(region) = self.region_for_mut(pos) {
+            let page = region.page_index(pos);
+            // The order actually used to satisfy the allocation may be
+            // larger than `order_for(num_pages)` (e.g. to meet a stricter
+            // alignment), so it has to be read back rather than recomputed.
+            let order = region.order_at(page);
+            region.mark(page, 1 << order, false);
+            region.used_pages -= 1 << order;
+            unsafe { region.dealloc_order(pos, order) };
+        }
+    }
+
+    fn total_pages(&self) -> usize {
+        self.regions[..self.region_count]
+            .iter()
+            .flatten()
+            .map(|r| r.num_pages)
+            .sum()
+    }
+
+    fn used_pages(&self) -> usize {
+        self.regions[..self.region_count]
+            .iter()
+            .flatten()
+            .map(|r| r.used_pages)
+            .sum()
+    }
+
+    fn available_pages(&self) -> usize {
+        self.total_pages() - self.used_pages()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_PAGE_SIZE: usize = 4096;
+    const TEST_PAGES: usize = 64;
+
+    #[repr(align(4096))]
+    struct AlignedBuf([u8; TEST_PAGE_SIZE * TEST_PAGES]);
+
+    fn new_allocator() -> (BuddyPageAllocator<TEST_PAGE_SIZE>, Box<AlignedBuf>) {
+        let buf = Box::new(AlignedBuf([0; TEST_PAGE_SIZE * TEST_PAGES]));
+        let start = buf.0.as_ptr() as usize;
+        let mut alloc = BuddyPageAllocator::<TEST_PAGE_SIZE>::new();
+        alloc.init(start, TEST_PAGE_SIZE * TEST_PAGES);
+        (alloc, buf)
+    }
+
+    #[test]
+    fn over_aligned_alloc_frees_the_whole_block() {
+        let (mut alloc, _buf) = new_allocator();
+
+        // Request a single page but with an alignment two orders above
+        // PAGE_SIZE, forcing alloc_pages to hand out a 4-page block.
+        let align_pow2 = (TEST_PAGE_SIZE * 4).trailing_zeros() as usize;
+        let addr = alloc.alloc_pages(1, align_pow2).unwrap();
+        assert_eq!(addr % (TEST_PAGE_SIZE * 4), 0);
+
+        let available_after_alloc = alloc.available_pages();
+        alloc.dealloc_pages(addr, 1);
+
+        // Freeing must reclaim all 4 pages the block actually used, not just
+        // the 1 page the caller originally asked for.
+        assert_eq!(alloc.available_pages(), available_after_alloc + 4);
+        assert_eq!(alloc.available_pages(), alloc.total_pages());
+
+        // Every page must be genuinely free and distinct: re-allocate the
+        // whole region one page at a time and check no address is handed
+        // out twice, which an aliased double-allocation would produce.
+        let mut seen = Vec::new();
+        for _ in 0..alloc.total_pages() {
+            let addr = alloc.alloc_pages(1, 0).unwrap();
+            assert!(!seen.contains(&addr), "page {:#x} handed out twice", addr);
+            seen.push(addr);
+        }
+    }
+}
@@ -15,12 +15,25 @@ use core::ptr::NonNull;
 ///
 /// For bytes area, 'count' records number of allocations.
 /// When it goes down to ZERO, free bytes-used area.
-/// For pages area, it will never be freed!
+/// For pages area, single pages released out of order are kept on a small
+/// intrusive free-list cache (threaded through the freed page itself) and
+/// reused by later single-page allocations, instead of leaking until the
+/// allocator is discarded. The page most recently bumped off `page_pos` is
+/// still reclaimed directly by decrementing `page_pos`.
 pub struct EarlyAllocator<const PAGE_SIZE: usize> {
     start: usize,
     end: usize,
     byte_pos: usize,
     page_pos: usize,
+    /// Head of the cache of freed single pages.
+    page_free_list: Option<NonNull<FreePageNode>>,
+    /// Number of pages currently sitting in `page_free_list`.
+    page_free_count: usize,
+}
+
+/// An intrusive free-list node stored in the first word of a cached page.
+struct FreePageNode {
+    next: Option<NonNull<FreePageNode>>,
 }
 
 impl<const PAGE_SIZE: usize> EarlyAllocator<PAGE_SIZE> {
@@ -30,6 +43,8 @@ impl<const PAGE_SIZE: usize> EarlyAllocator<PAGE_SIZE> {
             end: 0,
             byte_pos: 0,
             page_pos: 0,
+            page_free_list: None,
+            page_free_count: 0,
         }
     }
 }
@@ -95,6 +110,17 @@ impl<const PAGE_SIZE: usize> PageAllocator for EarlyAllocator<PAGE_SIZE> {
     /// Allocate contiguous memory pages with given count and alignment.
     fn alloc_pages(&mut self, num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
         let align = 1 << align_pow2;
+
+        // A single cached page is already page-aligned, so it can satisfy
+        // any request whose alignment is no stricter than that.
+        if num_pages == 1 && align <= PAGE_SIZE {
+            if let Some(node) = self.page_free_list {
+                self.page_free_list = unsafe { node.as_ref().next };
+                self.page_free_count -= 1;
+                return Ok(node.as_ptr() as usize);
+            }
+        }
+
         let mask = align - 1;
         let p_end = self.page_pos & !mask;
         let p_pos = p_end - num_pages * PAGE_SIZE;
@@ -110,6 +136,18 @@ impl<const PAGE_SIZE: usize> PageAllocator for EarlyAllocator<PAGE_SIZE> {
         let p_end = pos + num_pages * PAGE_SIZE;
         if p_end == self.page_pos {
             self.page_pos = pos;
+            return;
+        }
+
+        // Can't coalesce a multi-page run into the free-list cache (it only
+        // threads single pages), so just cache single pages released early.
+        if num_pages == 1 {
+            let node = pos as *mut FreePageNode;
+            unsafe {
+                (*node).next = self.page_free_list;
+                self.page_free_list = Some(NonNull::new_unchecked(node));
+            }
+            self.page_free_count += 1;
         }
     }
 
@@ -120,11 +158,11 @@ impl<const PAGE_SIZE: usize> PageAllocator for EarlyAllocator<PAGE_SIZE> {
 
     /// Returns the number of allocated memory pages.
     fn used_pages(&self) -> usize {
-        (self.end - self.page_pos) / PAGE_SIZE
+        (self.end - self.page_pos) / PAGE_SIZE - self.page_free_count
     }
 
     /// Returns the number of available memory pages.
     fn available_pages(&self) -> usize {
-        (self.page_pos - self.byte_pos) / PAGE_SIZE
+        (self.page_pos - self.byte_pos) / PAGE_SIZE + self.page_free_count
     }
 }
@@ -0,0 +1,343 @@
+#![cfg_attr(not(test), no_std)]
+
+use allocator::{AllocResult, BaseAllocator, ByteAllocator, PageAllocator};
+use core::alloc::Layout;
+use core::ptr::NonNull;
+
+/// Fixed size classes served out of slab pages, smallest first. All of them
+/// are powers of two, and the slot area of each page is aligned to the
+/// class size (see [`SlabPage::header_bytes`]), so any requested alignment
+/// up to the class size divides the start address of every slot.
+///
+/// Deliberately stops short of `PAGE_SIZE / 2`: for a class that large,
+/// aligning the header up to the class size (see [`SlabPage::header_bytes`])
+/// would consume the entire first class-sized chunk of the page as padding,
+/// leaving room for exactly one slot — no packing benefit over just routing
+/// the request straight to a whole page, which is already what happens for
+/// anything bigger than the largest class here.
+const SIZE_CLASSES: [usize; 8] = [8, 16, 32, 64, 128, 256, 512, 1024];
+/// Number of `u64` words used for a page's slot-availability bitmap, i.e. the
+/// largest number of slots a single page can be carved into.
+const BITMAP_WORDS: usize = 8;
+
+/// One page carved into equal-size slots for a single size class.
+///
+/// The header sits at the very start of the page; slot storage starts at the
+/// next `class_size` boundary after it, so every slot is aligned to
+/// `class_size` rather than merely to 8 bytes.
+#[repr(C)]
+struct SlabPage {
+    next: Option<NonNull<SlabPage>>,
+    total_slots: usize,
+    free_count: usize,
+    /// One bit per slot: `1` means the slot is free.
+    avail: [u64; BITMAP_WORDS],
+}
+
+impl SlabPage {
+    fn header_bytes(class_size: usize) -> usize {
+        (core::mem::size_of::<SlabPage>() + class_size - 1) & !(class_size - 1)
+    }
+
+    fn data_start(page_addr: usize, class_size: usize) -> usize {
+        page_addr + Self::header_bytes(class_size)
+    }
+
+    fn total_slots(page_size: usize, class_size: usize) -> usize {
+        let capacity = (page_size - Self::header_bytes(class_size)) / class_size;
+        capacity.min(BITMAP_WORDS * 64)
+    }
+
+    /// Initializes a fresh page at `page_addr` as a slab page for `class_size`.
+    unsafe fn init(page_addr: usize, page_size: usize, class_size: usize) -> NonNull<SlabPage> {
+        let total_slots = Self::total_slots(page_size, class_size);
+        let page = page_addr as *mut SlabPage;
+        (*page).next = None;
+        (*page).total_slots = total_slots;
+        (*page).free_count = total_slots;
+        (*page).avail = [0; BITMAP_WORDS];
+        for slot in 0..total_slots {
+            (*page).avail[slot / 64] |= 1 << (slot % 64);
+        }
+        NonNull::new_unchecked(page)
+    }
+
+    unsafe fn alloc_slot(&mut self, page_addr: usize, class_size: usize) -> Option<usize> {
+        for word in 0..BITMAP_WORDS {
+            if self.avail[word] != 0 {
+                let bit = self.avail[word].trailing_zeros() as usize;
+                self.avail[word] &= !(1 << bit);
+                self.free_count -= 1;
+                let slot = word * 64 + bit;
+                return Some(Self::data_start(page_addr, class_size) + slot * class_size);
+            }
+        }
+        None
+    }
+
+    unsafe fn free_slot(&mut self, page_addr: usize, class_size: usize, addr: usize) {
+        let slot = (addr - Self::data_start(page_addr, class_size)) / class_size;
+        self.avail[slot / 64] |= 1 << (slot % 64);
+        self.free_count += 1;
+    }
+
+    fn is_full(&self) -> bool {
+        self.free_count == 0
+    }
+
+    fn is_empty(&self) -> bool {
+        self.free_count == self.total_slots
+    }
+}
+
+/// Unlinks `target` from the singly-linked list rooted at `head`.
+unsafe fn unlink_page(head: &mut Option<NonNull<SlabPage>>, target: NonNull<SlabPage>) {
+    let mut cur = *head;
+    let mut prev: Option<NonNull<SlabPage>> = None;
+    while let Some(mut page) = cur {
+        if page == target {
+            match prev {
+                Some(mut p) => p.as_mut().next = page.as_ref().next,
+                None => *head = page.as_ref().next,
+            }
+            page.as_mut().next = None;
+            return;
+        }
+        prev = cur;
+        cur = page.as_ref().next;
+    }
+}
+
+/// Inserts `page` into the list rooted at `head`, kept sorted by ascending
+/// `free_count`, so the head is the fullest page (possibly one with no room
+/// left) and a walk from the head meets the fullest page that still has room
+/// before any emptier one (used by the medium-class lists to improve locality).
+unsafe fn insert_sorted(head: &mut Option<NonNull<SlabPage>>, mut page: NonNull<SlabPage>) {
+    let mut cur = *head;
+    let mut prev: Option<NonNull<SlabPage>> = None;
+    while let Some(node) = cur {
+        if node.as_ref().free_count >= page.as_ref().free_count {
+            break;
+        }
+        prev = cur;
+        cur = node.as_ref().next;
+    }
+    page.as_mut().next = cur;
+    match prev {
+        Some(mut p) => p.as_mut().next = Some(page),
+        None => *head = Some(page),
+    }
+}
+
+/// A slab-style front end for small and medium allocations, backed by a
+/// [`PageAllocator`] for its actual memory.
+///
+/// Requests that fit a [`SIZE_CLASSES`] entry up to `PAGE_SIZE / 8` get a
+/// whole dedicated page of equal-size slots ("small" classes); requests that
+/// fit a larger entry are packed several-per-page, always into the fullest
+/// page with room, to keep pages dense ("medium" classes). Anything bigger
+/// than the largest size class is allocated directly as whole pages.
+pub struct SlabByteAllocator<P: PageAllocator> {
+    page_alloc: P,
+    small_heads: [Option<NonNull<SlabPage>>; SIZE_CLASSES.len()],
+    medium_heads: [Option<NonNull<SlabPage>>; SIZE_CLASSES.len()],
+    used_bytes: usize,
+}
+
+unsafe impl<P: PageAllocator> Send for SlabByteAllocator<P> {}
+unsafe impl<P: PageAllocator> Sync for SlabByteAllocator<P> {}
+
+impl<P: PageAllocator> SlabByteAllocator<P> {
+    pub const fn new(page_alloc: P) -> Self {
+        Self {
+            page_alloc,
+            small_heads: [None; SIZE_CLASSES.len()],
+            medium_heads: [None; SIZE_CLASSES.len()],
+            used_bytes: 0,
+        }
+    }
+
+    fn class_for(size: usize) -> Option<usize> {
+        SIZE_CLASSES.iter().position(|&class| class >= size)
+    }
+
+    fn is_small_class(class_size: usize) -> bool {
+        class_size <= P::PAGE_SIZE / 8
+    }
+
+    fn head_for(&mut self, idx: usize) -> &mut Option<NonNull<SlabPage>> {
+        if Self::is_small_class(SIZE_CLASSES[idx]) {
+            &mut self.small_heads[idx]
+        } else {
+            &mut self.medium_heads[idx]
+        }
+    }
+
+    /// Allocates a single fresh backing page and carves it into slots.
+    fn new_page(&mut self, class_size: usize) -> AllocResult<NonNull<SlabPage>> {
+        let page_addr = self.page_alloc.alloc_pages(1, 0)?;
+        Ok(unsafe { SlabPage::init(page_addr, P::PAGE_SIZE, class_size) })
+    }
+
+    fn alloc_in_class(&mut self, idx: usize) -> AllocResult<NonNull<u8>> {
+        let class_size = SIZE_CLASSES[idx];
+        let small = Self::is_small_class(class_size);
+
+        // Try the existing pages first.
+        let mut cur = *self.head_for(idx);
+        while let Some(mut page) = cur {
+            unsafe {
+                if !page.as_ref().is_full() {
+                    let page_addr = page.as_ptr() as usize;
+                    let addr = page.as_mut().alloc_slot(page_addr, class_size).unwrap();
+                    if !small {
+                        // Re-sort: this page just got fuller.
+                        unlink_page(self.head_for(idx), page);
+                        insert_sorted(self.head_for(idx), page);
+                    }
+                    self.used_bytes += class_size;
+                    return Ok(NonNull::new_unchecked(addr as *mut u8));
+                }
+                cur = page.as_ref().next;
+            }
+        }
+
+        // All existing pages (if any) are full; get a fresh one.
+        let mut page = self.new_page(class_size)?;
+        let page_addr = page.as_ptr() as usize;
+        let addr = unsafe { page.as_mut().alloc_slot(page_addr, class_size).unwrap() };
+        if small {
+            unsafe { (*page.as_ptr()).next = *self.head_for(idx) };
+            *self.head_for(idx) = Some(page);
+        } else {
+            unsafe { insert_sorted(self.head_for(idx), page) };
+        }
+        self.used_bytes += class_size;
+        Ok(unsafe { NonNull::new_unchecked(addr as *mut u8) })
+    }
+
+    fn dealloc_in_class(&mut self, idx: usize, pos: NonNull<u8>) {
+        let class_size = SIZE_CLASSES[idx];
+        let page_addr = (pos.as_ptr() as usize) & !(P::PAGE_SIZE - 1);
+        let mut page = unsafe { NonNull::new_unchecked(page_addr as *mut SlabPage) };
+
+        unsafe {
+            page.as_mut().free_slot(page_addr, class_size, pos.as_ptr() as usize);
+
+            if page.as_ref().is_empty() {
+                unlink_page(self.head_for(idx), page);
+                self.page_alloc.dealloc_pages(page_addr, 1);
+            } else if !Self::is_small_class(class_size) {
+                // Re-sort: this page just got emptier.
+                unlink_page(self.head_for(idx), page);
+                insert_sorted(self.head_for(idx), page);
+            }
+        }
+        self.used_bytes -= class_size;
+    }
+
+    fn whole_page_align_pow2(layout: Layout) -> usize {
+        if layout.align() > P::PAGE_SIZE {
+            layout.align().trailing_zeros() as usize
+        } else {
+            0
+        }
+    }
+}
+
+impl<P: PageAllocator> BaseAllocator for SlabByteAllocator<P> {
+    fn init(&mut self, start: usize, size: usize) {
+        self.page_alloc.init(start, size);
+    }
+
+    fn add_memory(&mut self, start: usize, size: usize) -> AllocResult {
+        self.page_alloc.add_memory(start, size)
+    }
+}
+
+impl<P: PageAllocator> ByteAllocator for SlabByteAllocator<P> {
+    fn alloc(&mut self, layout: Layout) -> AllocResult<NonNull<u8>> {
+        let need = layout.size().max(layout.align()).max(1);
+
+        if let Some(idx) = Self::class_for(need) {
+            return self.alloc_in_class(idx);
+        }
+
+        let num_pages = (layout.size() + P::PAGE_SIZE - 1) / P::PAGE_SIZE;
+        let align_pow2 = Self::whole_page_align_pow2(layout);
+        let addr = self.page_alloc.alloc_pages(num_pages.max(1), align_pow2)?;
+        self.used_bytes += num_pages.max(1) * P::PAGE_SIZE;
+        Ok(unsafe { NonNull::new_unchecked(addr as *mut u8) })
+    }
+
+    fn dealloc(&mut self, pos: NonNull<u8>, layout: Layout) {
+        let need = layout.size().max(layout.align()).max(1);
+
+        if let Some(idx) = Self::class_for(need) {
+            self.dealloc_in_class(idx, pos);
+            return;
+        }
+
+        let num_pages = (layout.size() + P::PAGE_SIZE - 1) / P::PAGE_SIZE;
+        self.page_alloc.dealloc_pages(pos.as_ptr() as usize, num_pages.max(1));
+        self.used_bytes -= num_pages.max(1) * P::PAGE_SIZE;
+    }
+
+    fn total_bytes(&self) -> usize {
+        self.page_alloc.total_pages() * P::PAGE_SIZE
+    }
+
+    fn used_bytes(&self) -> usize {
+        self.used_bytes
+    }
+
+    fn available_bytes(&self) -> usize {
+        self.page_alloc.available_pages() * P::PAGE_SIZE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe fn new_page(free_count: usize) -> NonNull<SlabPage> {
+        let page = Box::new(SlabPage {
+            next: None,
+            total_slots: 64,
+            free_count,
+            avail: [0; BITMAP_WORDS],
+        });
+        NonNull::new_unchecked(Box::into_raw(page))
+    }
+
+    #[test]
+    fn insert_sorted_keeps_fullest_page_at_the_head() {
+        let free_counts = [40, 5, 60, 20, 0];
+        let mut head: Option<NonNull<SlabPage>> = None;
+
+        unsafe {
+            for &free_count in &free_counts {
+                insert_sorted(&mut head, new_page(free_count));
+            }
+
+            let mut seen = Vec::new();
+            let mut cur = head;
+            while let Some(page) = cur {
+                seen.push(page.as_ref().free_count);
+                cur = page.as_ref().next;
+            }
+
+            let mut expected = free_counts.to_vec();
+            expected.sort_unstable();
+            assert_eq!(
+                seen, expected,
+                "list must be sorted ascending by free_count, fullest page first"
+            );
+
+            while let Some(page) = head {
+                head = page.as_ref().next;
+                drop(Box::from_raw(page.as_ptr()));
+            }
+        }
+    }
+}